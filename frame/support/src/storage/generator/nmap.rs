@@ -37,7 +37,8 @@ use crate::{
 			EncodeLikeTuple, HasKeyPrefix, HasReversibleKeyPrefix, KeyGenerator,
 			ReversibleKeyGenerator, TupleToEncodedIter,
 		},
-		unhashed, PrefixIterator, StorageAppend,
+		unhashed, MultiRemovalResults, PrefixIterator, StorageAppend, StorageDecodeLength,
+		StorageTryAppend,
 	},
 	Never,
 };
@@ -45,6 +46,20 @@ use codec::{Decode, Encode, EncodeLike, FullCodec};
 #[cfg(not(feature = "std"))]
 use sp_std::prelude::*;
 
+/// The smallest raw key that sorts after every key starting with `prefix`, used to build an
+/// already-exhausted iterator (e.g. when resuming from a stale cursor). If every byte of `prefix`
+/// is `0xff` no such key exists, and `prefix` itself is returned.
+fn prefix_upper_bound(prefix: &[u8]) -> Vec<u8> {
+	let mut upper_bound = prefix.to_vec();
+	while let Some(last) = upper_bound.pop() {
+		if last < 0xff {
+			upper_bound.push(last + 1);
+			return upper_bound
+		}
+	}
+	prefix.to_vec()
+}
+
 /// Generator for `StorageNMap` used by `decl_storage` and storage types.
 ///
 /// By default each key value is stored at:
@@ -203,6 +218,22 @@ where
 		unhashed::kill_prefix(&Self::storage_n_map_partial_key(partial_key), limit)
 	}
 
+	/// Remove up to `limit` keys under `partial_key`, resuming from `maybe_cursor` if given. The
+	/// returned [`MultiRemovalResults::maybe_cursor`] is `None` once the prefix is fully drained,
+	/// or the raw key to pass back in as `maybe_cursor` on the next call otherwise, so a prefix
+	/// larger than a single block's weight budget can be removed incrementally.
+	fn clear_prefix<KP>(
+		partial_key: KP,
+		limit: u32,
+		maybe_cursor: Option<&[u8]>,
+	) -> MultiRemovalResults
+	where
+		K: HasKeyPrefix<KP>,
+	{
+		let prefix = Self::storage_n_map_partial_key(partial_key);
+		unhashed::clear_prefix(&prefix, Some(limit), maybe_cursor)
+	}
+
 	fn iter_prefix_values<KP>(partial_key: KP) -> PrefixIterator<V>
 	where
 		K: HasKeyPrefix<KP>,
@@ -281,6 +312,24 @@ where
 		sp_io::storage::append(&final_key, item.encode());
 	}
 
+	fn try_append<Item, EncodeLikeItem, KArg>(key: KArg, item: EncodeLikeItem) -> Result<(), ()>
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		Item: Encode,
+		EncodeLikeItem: EncodeLike<Item>,
+		V: StorageTryAppend<Item>,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		let current_len = V::decode_len(&final_key).unwrap_or(0);
+
+		if current_len < V::bound() {
+			sp_io::storage::append(&final_key, item.encode());
+			Ok(())
+		} else {
+			Err(())
+		}
+	}
+
 	fn migrate_keys<KArg>(key: KArg, hash_fns: K::HArg) -> Option<V>
 	where
 		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
@@ -337,6 +386,32 @@ impl<K: ReversibleKeyGenerator, V: FullCodec, G: StorageNMap<K, V>>
 		iter
 	}
 
+	fn iter_key_prefix<KP>(kp: KP) -> PrefixIterator<<K as HasKeyPrefix<KP>>::Suffix>
+	where
+		K: HasReversibleKeyPrefix<KP>,
+	{
+		let prefix = G::storage_n_map_partial_key(kp);
+		PrefixIterator {
+			prefix: prefix.clone(),
+			previous_key: prefix,
+			drain: false,
+			closure: |raw_key_without_prefix, _raw_value| K::decode_partial_key(raw_key_without_prefix),
+		}
+	}
+
+	fn iter_keys() -> PrefixIterator<K::Key> {
+		let prefix = G::prefix_hash();
+		PrefixIterator {
+			prefix: prefix.clone(),
+			previous_key: prefix,
+			drain: false,
+			closure: |raw_key_without_prefix, _raw_value| {
+				let (final_key, _) = K::decode_final_key(raw_key_without_prefix)?;
+				Ok(final_key)
+			},
+		}
+	}
+
 	fn iter() -> Self::Iterator {
 		let prefix = G::prefix_hash();
 		Self::Iterator {
@@ -356,6 +431,43 @@ impl<K: ReversibleKeyGenerator, V: FullCodec, G: StorageNMap<K, V>>
 		iterator
 	}
 
+	/// Enumerate all `(suffix, value)` pairs under `kp`, starting after `starting_raw_key` rather than from the
+	/// beginning of the prefix. The first item yielded is the one *after* `starting_raw_key`, so
+	/// a caller can resume from the last raw key it was given without seeing it again.
+	///
+	/// If `starting_raw_key` does not start with the prefix generated from `kp`, the cursor is
+	/// stale (e.g. it was produced for a different partial key) and the returned iterator yields
+	/// nothing, rather than panicking.
+	fn iter_prefix_from<KP>(
+		kp: KP,
+		starting_raw_key: Vec<u8>,
+	) -> PrefixIterator<(<K as HasKeyPrefix<KP>>::Suffix, V)>
+	where
+		K: HasReversibleKeyPrefix<KP>,
+	{
+		let mut iter = Self::iter_prefix(kp);
+		iter.previous_key = if starting_raw_key.starts_with(&iter.prefix) {
+			starting_raw_key
+		} else {
+			prefix_upper_bound(&iter.prefix)
+		};
+		iter
+	}
+
+	/// Same as [`Self::iter_prefix_from`] but over the whole map rather than a single prefix.
+	///
+	/// If `starting_raw_key` does not start with the map's prefix, the cursor is stale and the
+	/// returned iterator yields nothing, rather than panicking.
+	fn iter_from(starting_raw_key: Vec<u8>) -> Self::Iterator {
+		let mut iter = Self::iter();
+		iter.previous_key = if starting_raw_key.starts_with(&iter.prefix) {
+			starting_raw_key
+		} else {
+			prefix_upper_bound(&iter.prefix)
+		};
+		iter
+	}
+
 	fn translate<O: Decode, F: FnMut(K::Key, O) -> Option<V>>(mut f: F) {
 		let prefix = G::prefix_hash();
 		let mut previous_key = prefix.clone();
@@ -387,12 +499,310 @@ impl<K: ReversibleKeyGenerator, V: FullCodec, G: StorageNMap<K, V>>
 	}
 }
 
+/// Marker used to build the storage key of the counter kept by [`CountedStorageNMap`].
+const COUNTER_STORAGE_PREFIX_MARKER: &[u8] = b"CounterFor";
+
+/// Variant of [`StorageNMap`] that additionally keeps track of how many keys are currently
+/// stored, without requiring a full `iter().count()` scan of the trie.
+///
+/// The counter is kept in its own storage item, at
+/// `Twox128(module_prefix) ++ Twox128("CounterFor" ++ storage_prefix)`, and is updated by every
+/// method below that can change whether a key is present: incremented when a key transitions
+/// from absent to present, decremented on the reverse transition. `swap` never changes the set
+/// of present keys and is therefore a plain pass-through.
+pub trait CountedStorageNMap<K: KeyGenerator, V: FullCodec>: StorageNMap<K, V> {
+	/// The key under which the number of elements currently stored is kept.
+	fn counter_storage_final_key() -> Vec<u8> {
+		let module_prefix_hashed = Twox128::hash(Self::module_prefix());
+
+		let mut counter_storage_prefix =
+			Vec::with_capacity(COUNTER_STORAGE_PREFIX_MARKER.len() + Self::storage_prefix().len());
+		counter_storage_prefix.extend_from_slice(COUNTER_STORAGE_PREFIX_MARKER);
+		counter_storage_prefix.extend_from_slice(Self::storage_prefix());
+		let storage_prefix_hashed = Twox128::hash(&counter_storage_prefix);
+
+		let mut final_key =
+			Vec::with_capacity(module_prefix_hashed.len() + storage_prefix_hashed.len());
+		final_key.extend_from_slice(&module_prefix_hashed[..]);
+		final_key.extend_from_slice(&storage_prefix_hashed[..]);
+
+		final_key
+	}
+
+	/// Read the number of elements currently stored.
+	fn count() -> u32 {
+		unhashed::get(&Self::counter_storage_final_key()).unwrap_or(0)
+	}
+
+	/// Set the number of elements currently stored, bypassing the usual increment/decrement
+	/// logic. Only meant to be used by [`initialize_counter`](Self::initialize_counter) and the
+	/// other methods of this trait.
+	fn set_count(count: u32) {
+		unhashed::put(&Self::counter_storage_final_key(), &count);
+	}
+
+	fn inc_count() {
+		Self::set_count(Self::count().saturating_add(1));
+	}
+
+	fn dec_count() {
+		Self::set_count(Self::count().saturating_sub(1));
+	}
+
+	/// Repair the counter by doing a full iteration over the underlying map, counting the keys
+	/// actually present. Intended to be called once from a migration, after which the counter
+	/// can be trusted again.
+	fn initialize_counter() -> u32 {
+		let prefix = Self::prefix_hash();
+		let mut previous_key = prefix.clone();
+		let mut count = 0u32;
+		while let Some(next) =
+			sp_io::storage::next_key(&previous_key).filter(|n| n.starts_with(&prefix))
+		{
+			previous_key = next;
+			count += 1;
+		}
+		Self::set_count(count);
+		count
+	}
+
+	fn insert<KArg, VArg>(key: KArg, val: VArg)
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		VArg: EncodeLike<V>,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		if !unhashed::exists(final_key.as_ref()) {
+			Self::inc_count();
+		}
+		unhashed::put(final_key.as_ref(), &val);
+	}
+
+	fn remove<KArg>(key: KArg)
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		if unhashed::exists(final_key.as_ref()) {
+			Self::dec_count();
+		}
+		unhashed::kill(final_key.as_ref());
+	}
+
+	fn take<KArg>(key: KArg) -> Self::Query
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		let value = unhashed::take(final_key.as_ref());
+		if value.is_some() {
+			Self::dec_count();
+		}
+		Self::from_optional_value_to_query(value)
+	}
+
+	fn swap<KOther, KArg1, KArg2>(key1: KArg1, key2: KArg2)
+	where
+		KOther: KeyGenerator,
+		KArg1: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		KArg2: EncodeLikeTuple<KOther::KArg> + TupleToEncodedIter,
+	{
+		// The two keys just exchange their (possibly absent) values, so the set of present keys
+		// is unchanged and the counter does not need adjusting.
+		<Self as storage::StorageNMap<K, V>>::swap::<KOther, _, _>(key1, key2)
+	}
+
+	fn mutate<KArg, R, F>(key: KArg, f: F) -> R
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		F: FnOnce(&mut Self::Query) -> R,
+	{
+		Self::try_mutate(key, |v| Ok::<R, Never>(f(v)))
+			.expect("`Never` can not be constructed; qed")
+	}
+
+	fn try_mutate<KArg, R, E, F>(key: KArg, f: F) -> Result<R, E>
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		F: FnOnce(&mut Self::Query) -> Result<R, E>,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		let existed = unhashed::exists(final_key.as_ref());
+		let mut val = Self::from_optional_value_to_query(unhashed::get(final_key.as_ref()));
+
+		let ret = f(&mut val);
+		if ret.is_ok() {
+			match Self::from_query_to_optional_value(val) {
+				Some(ref val) => unhashed::put(final_key.as_ref(), val),
+				None => unhashed::kill(final_key.as_ref()),
+			}
+			match (existed, unhashed::exists(final_key.as_ref())) {
+				(false, true) => Self::inc_count(),
+				(true, false) => Self::dec_count(),
+				_ => {},
+			}
+		}
+		ret
+	}
+
+	fn mutate_exists<KArg, R, F>(key: KArg, f: F) -> R
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		F: FnOnce(&mut Option<V>) -> R,
+	{
+		Self::try_mutate_exists(key, |v| Ok::<R, Never>(f(v)))
+			.expect("`Never` can not be constructed; qed")
+	}
+
+	fn try_mutate_exists<KArg, R, E, F>(key: KArg, f: F) -> Result<R, E>
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		F: FnOnce(&mut Option<V>) -> Result<R, E>,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		let mut val = unhashed::get::<V>(final_key.as_ref());
+		let existed = val.is_some();
+
+		let ret = f(&mut val);
+		if ret.is_ok() {
+			let exists = val.is_some();
+			match val {
+				Some(ref val) => unhashed::put(final_key.as_ref(), val),
+				None => unhashed::kill(final_key.as_ref()),
+			}
+			match (existed, exists) {
+				(false, true) => Self::inc_count(),
+				(true, false) => Self::dec_count(),
+				_ => {},
+			}
+		}
+		ret
+	}
+
+	/// Append `item` to the value at `key`.
+	///
+	/// Like [`StorageNMap::append`], this can create a fresh encoded collection under `key`, so
+	/// it must also increment the counter when `key` transitions from absent to present.
+	fn append<Item, EncodeLikeItem, KArg>(key: KArg, item: EncodeLikeItem)
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		Item: Encode,
+		EncodeLikeItem: EncodeLike<Item>,
+		V: StorageAppend<Item>,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		if !unhashed::exists(final_key.as_ref()) {
+			Self::inc_count();
+		}
+		sp_io::storage::append(&final_key, item.encode());
+	}
+
+	/// Same as [`Self::append`], but bails out with `Err(())` instead of growing `V` past its
+	/// [`StorageTryAppend::bound`].
+	fn try_append<Item, EncodeLikeItem, KArg>(key: KArg, item: EncodeLikeItem) -> Result<(), ()>
+	where
+		KArg: EncodeLikeTuple<K::KArg> + TupleToEncodedIter,
+		Item: Encode,
+		EncodeLikeItem: EncodeLike<Item>,
+		V: StorageTryAppend<Item>,
+	{
+		let final_key = Self::storage_n_map_final_key::<K, _>(key);
+		let current_len = V::decode_len(&final_key).unwrap_or(0);
+		if current_len >= V::bound() {
+			return Err(())
+		}
+
+		if !unhashed::exists(final_key.as_ref()) {
+			Self::inc_count();
+		}
+		sp_io::storage::append(&final_key, item.encode());
+		Ok(())
+	}
+
+	/// Remove all keys under `partial_key`, decrementing the counter by the number of unique keys
+	/// actually removed.
+	///
+	/// Implemented on top of [`Self::clear_prefix`] rather than the raw
+	/// `sp_io::KillStorageResult` so the counter is adjusted by the precise number of keys
+	/// removed (`unique`), not the coarse backend-node count the host function reports.
+	fn remove_prefix<KP>(partial_key: KP, limit: Option<u32>) -> sp_io::KillStorageResult
+	where
+		K: HasKeyPrefix<KP>,
+	{
+		let result = Self::clear_prefix(partial_key, limit.unwrap_or(u32::MAX), None);
+		if result.maybe_cursor.is_none() {
+			sp_io::KillStorageResult::AllRemoved(result.backend)
+		} else {
+			sp_io::KillStorageResult::SomeRemaining(result.backend)
+		}
+	}
+
+	/// Same as [`StorageNMap::clear_prefix`], decrementing the counter by the number of unique
+	/// keys removed this call so a chunked deletion across several blocks keeps the counter
+	/// correct after every call, not just once the prefix is fully drained.
+	fn clear_prefix<KP>(
+		partial_key: KP,
+		limit: u32,
+		maybe_cursor: Option<&[u8]>,
+	) -> MultiRemovalResults
+	where
+		K: HasKeyPrefix<KP>,
+	{
+		let result =
+			<Self as storage::StorageNMap<K, V>>::clear_prefix(partial_key, limit, maybe_cursor);
+		Self::set_count(Self::count().saturating_sub(result.unique));
+		result
+	}
+
+	/// Drain all keys under `kp`, decrementing the counter by one for every item the
+	/// returned iterator actually yields. Dropping the iterator before it is exhausted therefore
+	/// still leaves the counter consistent with what is left in storage.
+	fn drain_prefix<KP>(kp: KP) -> CountedStorageNMapDrainPrefix<Self, K, V, KP>
+	where
+		K: ReversibleKeyGenerator + HasReversibleKeyPrefix<KP>,
+	{
+		CountedStorageNMapDrainPrefix {
+			inner: <Self as storage::IterableStorageNMap<K, V>>::drain_prefix(kp),
+			_phantom: Default::default(),
+		}
+	}
+}
+
+/// Iterator returned by [`CountedStorageNMap::drain_prefix`]. Decrements the counter lazily, one
+/// item at a time, as the iterator is advanced.
+pub struct CountedStorageNMapDrainPrefix<G, K: HasKeyPrefix<KP>, V, KP> {
+	inner: PrefixIterator<(<K as HasKeyPrefix<KP>>::Suffix, V)>,
+	_phantom: sp_std::marker::PhantomData<G>,
+}
+
+impl<G, K, V, KP> Iterator for CountedStorageNMapDrainPrefix<G, K, V, KP>
+where
+	G: CountedStorageNMap<K, V>,
+	K: ReversibleKeyGenerator + HasReversibleKeyPrefix<KP>,
+	V: FullCodec,
+{
+	type Item = (<K as HasKeyPrefix<KP>>::Suffix, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let item = self.inner.next();
+		if item.is_some() {
+			G::dec_count();
+		}
+		item
+	}
+}
+
 /// Test iterators for StorageNMap
 #[cfg(test)]
 mod test_iterators {
 	use crate::{
 		hash::StorageHasher,
-		storage::{generator::StorageNMap, unhashed, IterableStorageNMap},
+		storage::{
+			generator::{CountedStorageNMap, StorageNMap},
+			unhashed, IterableStorageNMap,
+		},
+		traits::ConstU32,
+		BoundedVec,
 	};
 	use codec::{Decode, Encode};
 
@@ -413,9 +823,42 @@ mod test_iterators {
 	crate::decl_storage! {
 		trait Store for Module<T: Config> as Test {
 			NMap: nmap hasher(blake2_128_concat) u16, hasher(twox_64_concat) u32 => u64;
+			NMapAppend: nmap hasher(blake2_128_concat) u16, hasher(twox_64_concat) u32 => Vec<u64>;
+			NMapBounded: nmap hasher(blake2_128_concat) u16, hasher(twox_64_concat) u32 => BoundedVec<u64, ConstU32<2>>;
+		}
+	}
+
+	/// Fully generic pass-through wrapper used to exercise [`CountedStorageNMap`] against the
+	/// existing test storages above, without needing to name their macro-generated key type.
+	struct Counted<S>(core::marker::PhantomData<S>);
+
+	impl<K: crate::storage::types::KeyGenerator, V: codec::FullCodec, S: StorageNMap<K, V>>
+		StorageNMap<K, V> for Counted<S>
+	{
+		type Query = S::Query;
+
+		fn module_prefix() -> &'static [u8] {
+			S::module_prefix()
+		}
+
+		fn storage_prefix() -> &'static [u8] {
+			S::storage_prefix()
+		}
+
+		fn from_optional_value_to_query(v: Option<V>) -> Self::Query {
+			S::from_optional_value_to_query(v)
+		}
+
+		fn from_query_to_optional_value(v: Self::Query) -> Option<V> {
+			S::from_query_to_optional_value(v)
 		}
 	}
 
+	impl<K: crate::storage::types::KeyGenerator, V: codec::FullCodec, S: StorageNMap<K, V>>
+		CountedStorageNMap<K, V> for Counted<S>
+	{
+	}
+
 	fn key_before_prefix(mut prefix: Vec<u8>) -> Vec<u8> {
 		let last = prefix.iter_mut().last().unwrap();
 		assert!(*last != 0, "mock function not implemented for this prefix");
@@ -453,6 +896,11 @@ mod test_iterators {
 
 			assert_eq!(NMap::iter_values().collect::<Vec<_>>(), vec![3, 0, 2, 1],);
 
+			assert_eq!(
+				NMap::iter_keys().collect::<Vec<_>>(),
+				vec![(3, 3), (0, 0), (2, 2), (1, 1)],
+			);
+
 			assert_eq!(
 				NMap::drain().collect::<Vec<_>>(),
 				vec![((3, 3), 3), ((0, 0), 0), ((2, 2), 2), ((1, 1), 1)],
@@ -486,6 +934,11 @@ mod test_iterators {
 				vec![1, 2, 0, 3],
 			);
 
+			assert_eq!(
+				NMap::iter_key_prefix((k1,)).collect::<Vec<_>>(),
+				vec![1, 2, 0, 3],
+			);
+
 			assert_eq!(
 				NMap::drain_prefix((k1,)).collect::<Vec<_>>(),
 				vec![(1, 1), (2, 2), (0, 0), (3, 3)],
@@ -538,4 +991,189 @@ mod test_iterators {
 			);
 		})
 	}
+
+	#[test]
+	fn n_map_iter_from_resumes_exclusive_of_cursor() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			for i in 0..4u32 {
+				NMap::insert((i as u16, i as u32), i as u64);
+			}
+
+			let full = NMap::iter().collect::<Vec<_>>();
+
+			let mut iter = NMap::iter();
+			assert_eq!(iter.next(), Some(full[0]));
+			let cursor = iter.previous_key.clone();
+
+			// `iter_from` must not yield `full[0]` again.
+			assert_eq!(NMap::iter_from(cursor).collect::<Vec<_>>(), full[1..].to_vec());
+		})
+	}
+
+	#[test]
+	fn n_map_iter_from_with_stale_cursor_is_empty() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			for i in 0..4u32 {
+				NMap::insert((i as u16, i as u32), i as u64);
+			}
+
+			// A cursor that does not belong to this map's prefix must not leak unrelated
+			// entries; the iterator should simply be empty instead of panicking.
+			let unrelated_prefix = NMapAppend::prefix_hash();
+			assert_eq!(NMap::iter_from(unrelated_prefix).collect::<Vec<_>>(), vec![]);
+		})
+	}
+
+	#[test]
+	fn counted_storage_n_map_tracks_insert_remove_take_and_mutate_exists() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			type C = Counted<NMap>;
+
+			assert_eq!(C::count(), 0);
+
+			C::insert((0u16, 0u32), 0u64);
+			C::insert((1u16, 1u32), 1u64);
+			assert_eq!(C::count(), 2);
+
+			// Re-inserting an already-present key must not double count.
+			C::insert((0u16, 0u32), 42u64);
+			assert_eq!(C::count(), 2);
+
+			C::remove((0u16, 0u32));
+			assert_eq!(C::count(), 1);
+
+			// Removing an already-absent key is a no-op for the counter.
+			C::remove((0u16, 0u32));
+			assert_eq!(C::count(), 1);
+
+			let _ = C::take((1u16, 1u32));
+			assert_eq!(C::count(), 0);
+
+			// `mutate_exists` going None -> Some increments, Some -> None decrements.
+			C::mutate_exists((2u16, 2u32), |v| *v = Some(9u64));
+			assert_eq!(C::count(), 1);
+			C::mutate_exists((2u16, 2u32), |v| *v = None);
+			assert_eq!(C::count(), 0);
+
+			C::insert((3u16, 3u32), 3u64);
+			C::insert((4u16, 4u32), 4u64);
+			C::set_count(0);
+			assert_eq!(C::initialize_counter(), 2);
+			assert_eq!(C::count(), 2);
+		})
+	}
+
+	#[test]
+	fn counted_storage_n_map_drain_prefix_decrements_lazily() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			type C = Counted<NMap>;
+			let k1: u16 = 9 << 8;
+
+			for i in 0..4u32 {
+				C::insert((k1, i), i as u64);
+			}
+			assert_eq!(C::count(), 4);
+
+			{
+				let mut iter = C::drain_prefix((k1,));
+				// Consume only half of the items, then drop the iterator early.
+				assert!(iter.next().is_some());
+				assert!(iter.next().is_some());
+			}
+
+			// Only the two consumed items were decremented; dropping the rest of the iterator
+			// must not lose track of what is still physically in storage.
+			assert_eq!(C::count(), 2);
+			assert_eq!(NMap::iter_prefix((k1,)).collect::<Vec<_>>().len(), 2);
+		})
+	}
+
+	#[test]
+	fn counted_storage_n_map_clear_prefix_is_precise_and_chunked() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			type C = Counted<NMap>;
+			let k1: u16 = 11 << 8;
+
+			for i in 0..5u32 {
+				C::insert((k1, i), i as u64);
+			}
+			assert_eq!(C::count(), 5);
+
+			let result = C::clear_prefix((k1,), 2, None);
+			assert_eq!(result.unique, 2);
+			assert!(result.maybe_cursor.is_some());
+			assert_eq!(C::count(), 3);
+
+			let cursor = result.maybe_cursor.unwrap();
+			let result = C::clear_prefix((k1,), 10, Some(&cursor));
+			assert!(result.maybe_cursor.is_none());
+			assert_eq!(C::count(), 0);
+		})
+	}
+
+	#[test]
+	fn counted_storage_n_map_remove_prefix_delegates_to_clear_prefix() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			type C = Counted<NMap>;
+			let k1: u16 = 13 << 8;
+
+			for i in 0..3u32 {
+				C::insert((k1, i), i as u64);
+			}
+			assert_eq!(C::count(), 3);
+
+			let result = C::remove_prefix((k1,), None);
+			assert!(matches!(result, sp_io::KillStorageResult::AllRemoved(_)));
+			assert_eq!(C::count(), 0);
+		})
+	}
+
+	#[test]
+	fn counted_storage_n_map_append_increments_only_once() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			type C = Counted<NMapAppend>;
+
+			C::append((0u16, 0u32), 1u64);
+			assert_eq!(C::count(), 1);
+
+			// Appending again to the same key must not double count, even though `append` can
+			// create the underlying encoded collection from scratch.
+			C::append((0u16, 0u32), 2u64);
+			assert_eq!(C::count(), 1);
+		})
+	}
+
+	#[test]
+	fn n_map_try_append_rejects_once_bound_reached() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let key = (0u16, 0u32);
+
+			assert_eq!(NMapBounded::try_append(key, 1u64), Ok(()));
+			assert_eq!(NMapBounded::try_append(key, 2u64), Ok(()));
+			let before = NMapBounded::try_get(key);
+
+			// The bound is 2, so a third append must be rejected rather than growing the
+			// `BoundedVec` past its compile-time limit, and the stored value must be untouched.
+			assert_eq!(NMapBounded::try_append(key, 3u64), Err(()));
+			assert_eq!(NMapBounded::try_get(key), before);
+		})
+	}
+
+	#[test]
+	fn counted_storage_n_map_try_append_leaves_count_untouched_on_err() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			type C = Counted<NMapBounded>;
+			let key = (0u16, 0u32);
+
+			assert_eq!(C::try_append(key, 1u64), Ok(()));
+			assert_eq!(C::count(), 1);
+			assert_eq!(C::try_append(key, 2u64), Ok(()));
+			assert_eq!(C::count(), 1);
+
+			// Rejected by the bound check: the counter must not move, since no new key was
+			// inserted and the existing one was left untouched.
+			assert_eq!(C::try_append(key, 3u64), Err(()));
+			assert_eq!(C::count(), 1);
+		})
+	}
 }